@@ -0,0 +1,40 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+//! Builtin system catalog views over the differential logging collections.
+
+/// Describes a builtin view installed in the `mz_catalog` schema.
+pub struct BuiltinView {
+    /// The view's name, e.g. `mz_merge_backlog`.
+    pub name: &'static str,
+    /// The schema the view is installed into.
+    pub schema: &'static str,
+    /// The `CREATE VIEW` body, querying the per-worker logging source directly.
+    pub sql: &'static str,
+}
+
+/// Per-operator, per-worker count and cumulative magnitude of merge shortfalls, sourced
+/// from `DifferentialLog::MergeBacklog` (see `dataflow::logging::differential`).
+pub const MZ_MERGE_BACKLOG: BuiltinView = BuiltinView {
+    name: "mz_merge_backlog",
+    schema: "mz_catalog",
+    sql: "CREATE VIEW mz_catalog.mz_merge_backlog AS SELECT
+    operator, worker, shortfall_events, shortfall_records
+FROM mz_catalog.mz_merge_backlog_internal",
+};
+
+/// Per-operator, per-worker merge latency histogram, bucketed to a power-of-two
+/// nanosecond bound, sourced from `DifferentialLog::MergeDuration`.
+pub const MZ_MERGE_DURATION: BuiltinView = BuiltinView {
+    name: "mz_merge_duration",
+    schema: "mz_catalog",
+    sql: "CREATE VIEW mz_catalog.mz_merge_duration AS SELECT
+    operator, worker, duration_ns, count
+FROM mz_catalog.mz_merge_duration_internal",
+};
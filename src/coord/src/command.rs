@@ -0,0 +1,36 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+//! Commands accepted by the coordinator from external clients.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use dataflow::logging::{handle_profile_command, ArrangementProfiler, ProfileCommand};
+use repr::Row;
+
+/// A command accepted by the coordinator.
+pub enum Command {
+    /// Requests an on-demand snapshot of arrangement memory footprint across all workers.
+    Profile(ProfileCommand),
+}
+
+/// Dispatches a [`Command`], given the coordinator's per-worker [`ArrangementProfiler`]
+/// handles (one per worker, as returned by `dataflow::logging::construct`).
+pub fn handle_command(
+    profilers: &[Rc<RefCell<ArrangementProfiler>>],
+    command: Command,
+) -> Vec<Row> {
+    match command {
+        Command::Profile(profile_command) => profilers
+            .iter()
+            .flat_map(|profiler| handle_profile_command(&profiler.borrow(), profile_command))
+            .collect(),
+    }
+}
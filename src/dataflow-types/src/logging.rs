@@ -0,0 +1,86 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+//! Types that identify and configure the logging dataflows maintained by each worker.
+
+use std::collections::HashMap;
+
+/// Logs specific to differential dataflow.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum DifferentialLog {
+    /// Live record and batch counts per operator and worker.
+    Arrangement,
+    /// Trace sharing counts per operator and worker.
+    Sharing,
+    /// Merge shortfall event and record counts per operator and worker.
+    MergeBacklog,
+    /// Merge latency, bucketed to a power of two, per operator and worker.
+    MergeDuration,
+}
+
+impl DifferentialLog {
+    /// The row columns that key this log's trace.
+    pub fn index_by(&self) -> Vec<usize> {
+        match self {
+            DifferentialLog::Arrangement => vec![0, 1],
+            DifferentialLog::Sharing => vec![0, 1],
+            DifferentialLog::MergeBacklog => vec![0, 1],
+            DifferentialLog::MergeDuration => vec![0, 1, 2],
+        }
+    }
+}
+
+/// Identifies a single logging collection maintained by a dataflow worker.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub enum LogVariant {
+    /// A log sourced from differential dataflow's internal logging events.
+    Differential(DifferentialLog),
+}
+
+impl LogVariant {
+    /// The row columns that key this variant's trace.
+    pub fn index_by(&self) -> Vec<usize> {
+        match self {
+            LogVariant::Differential(log) => log.index_by(),
+        }
+    }
+}
+
+/// Configuration for a dataflow worker's logging dataflows.
+#[derive(Debug, Clone)]
+pub struct LoggingConfig {
+    /// The interval, in nanoseconds, at which logging updates are rounded up and
+    /// materialized.
+    pub granularity_ns: u128,
+    /// The logs to actively maintain as arrangements, and the columns that key them.
+    pub active_logs: HashMap<LogVariant, Vec<usize>>,
+    /// An external sink to additionally tee raw differential logging events to, for
+    /// offline analysis -- see `dataflow::logging::differential::construct`. A
+    /// `file://path` prefix names a per-worker file path; anything else is a `host:port`
+    /// connected to over TCP.
+    pub capture_address: Option<String>,
+}
+
+impl LoggingConfig {
+    /// Constructs a `LoggingConfig` from parsed CLI/config values.
+    ///
+    /// `capture_address` is stored as given, unparsed: validation of the address happens
+    /// lazily when a worker opens the sink.
+    pub fn new(
+        granularity_ns: u128,
+        active_logs: HashMap<LogVariant, Vec<usize>>,
+        capture_address: Option<String>,
+    ) -> Self {
+        LoggingConfig {
+            granularity_ns,
+            active_logs,
+            capture_address,
+        }
+    }
+}
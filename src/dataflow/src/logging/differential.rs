@@ -9,6 +9,8 @@
 
 //! Logging dataflows for events generated by differential dataflow.
 
+use std::io::Write;
+use std::net::ToSocketAddrs;
 use std::time::Duration;
 
 use differential_dataflow::logging::DifferentialEvent;
@@ -21,18 +23,143 @@ use super::{DifferentialLog, LogVariant};
 use crate::arrangement::KeysValsHandle;
 use repr::{Datum, RowPacker, Timestamp};
 
+/// Timeout for connecting to a TCP capture sink, so an unreachable endpoint cannot stall
+/// dataflow construction.
+const CAPTURE_CONNECT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Where a `capture_address` points: a per-worker file path, or a TCP endpoint.
+#[derive(Debug, PartialEq, Eq)]
+enum CaptureTarget<'a> {
+    File(&'a str),
+    Tcp(&'a str),
+}
+
+/// Parses `capture_address`: a `file://path` prefix names a path (one file per worker is
+/// opened by appending the worker index); anything else is a host:port to connect to.
+fn parse_capture_address(address: &str) -> CaptureTarget<'_> {
+    match address.strip_prefix("file://") {
+        Some(path) => CaptureTarget::File(path),
+        None => CaptureTarget::Tcp(address),
+    }
+}
+
+/// Opens the external capture sink named by `capture_address`, if any.
+///
+/// A sink that cannot be opened is replaced with a no-op writer and a warning is logged,
+/// since a broken downstream consumer should never take down logging.
+fn open_capture_sink(address: &str, worker_index: usize) -> Box<dyn Write> {
+    match parse_capture_address(address) {
+        CaptureTarget::File(path) => {
+            let path = format!("{}.{}", path, worker_index);
+            match std::fs::OpenOptions::new().create(true).append(true).open(&path) {
+                Ok(file) => Box::new(std::io::BufWriter::new(file)),
+                Err(err) => {
+                    log::warn!("logging capture: could not open {}: {}", path, err);
+                    Box::new(std::io::sink())
+                }
+            }
+        }
+        CaptureTarget::Tcp(address) => {
+            let stream = address
+                .to_socket_addrs()
+                .ok()
+                .and_then(|mut addrs| addrs.next())
+                .and_then(|addr| {
+                    std::net::TcpStream::connect_timeout(&addr, CAPTURE_CONNECT_TIMEOUT).ok()
+                });
+            match stream {
+                Some(stream) => Box::new(stream),
+                None => {
+                    log::warn!("logging capture: could not connect to {}", address);
+                    Box::new(std::io::sink())
+                }
+            }
+        }
+    }
+}
+
+/// Rounds `elapsed_ns` down to the nearest power of two, so latency buckets stay bounded.
+fn latency_bucket_ns(elapsed_ns: u64) -> u64 {
+    if elapsed_ns == 0 {
+        0
+    } else {
+        1u64 << (63 - elapsed_ns.leading_zeros())
+    }
+}
+
+/// Hard cap on in-flight merge starts tracked per worker. A merge whose completion never
+/// arrives (its operator was torn down, or the merge was abandoned) would otherwise leak
+/// in `MergeDurationTracker::starts` for the lifetime of the worker; once the cap is hit,
+/// the oldest pending start is evicted to make room for new ones.
+const MAX_PENDING_MERGES: usize = 1 << 16;
+
+/// Correlates the start (`complete: None`) and completion (`complete: Some(_)`) halves of a
+/// `Merge` event, keyed by `(operator, worker, scale)` so overlapping merges on the same
+/// operator don't collide.
+struct MergeDurationTracker {
+    starts: std::collections::HashMap<(usize, WorkerIdentifier, usize), Duration>,
+    capacity: usize,
+}
+
+impl Default for MergeDurationTracker {
+    fn default() -> Self {
+        MergeDurationTracker::with_capacity(MAX_PENDING_MERGES)
+    }
+}
+
+impl MergeDurationTracker {
+    fn with_capacity(capacity: usize) -> Self {
+        MergeDurationTracker {
+            starts: std::collections::HashMap::new(),
+            capacity,
+        }
+    }
+
+    /// Folds in one `Merge` event, returning `(operator, worker, latency bucket)` once the
+    /// matching completion for a start arrives. A completion with no recorded start (e.g. a
+    /// worker restart) is ignored.
+    fn observe(
+        &mut self,
+        worker: WorkerIdentifier,
+        ts: Duration,
+        event: &differential_dataflow::logging::MergeEvent,
+    ) -> Option<(usize, WorkerIdentifier, u64)> {
+        let key = (event.operator, worker, event.scale);
+        if event.complete.is_none() {
+            if self.starts.len() >= self.capacity && !self.starts.contains_key(&key) {
+                if let Some(oldest) = self.starts.iter().min_by_key(|(_, &ts)| ts).map(|(&k, _)| k) {
+                    self.starts.remove(&oldest);
+                }
+            }
+            self.starts.insert(key, ts);
+            None
+        } else {
+            let start = self.starts.remove(&key)?;
+            let elapsed_ns = ts.saturating_sub(start).as_nanos() as u64;
+            Some((event.operator, worker, latency_bucket_ns(elapsed_ns)))
+        }
+    }
+}
+
 /// Constructs the logging dataflows and returns a logger and trace handles.
+///
+/// `profiler` is fed every replayed event, independent of the continuously-maintained
+/// collections built below; an operator can later drive it through [`handle_profile_command`]
+/// to pull a point-in-time snapshot instead of waiting on the always-on `Arrangement` view.
 pub fn construct<A: Allocate>(
     worker: &mut timely::worker::Worker<A>,
     config: &dataflow_types::logging::LoggingConfig,
     linked: std::rc::Rc<EventLink<Timestamp, (Duration, WorkerIdentifier, DifferentialEvent)>>,
+    profiler: std::rc::Rc<std::cell::RefCell<ArrangementProfiler>>,
 ) -> std::collections::HashMap<LogVariant, (Vec<usize>, KeysValsHandle)> {
     let granularity_ms = std::cmp::max(1, config.granularity_ns / 1_000_000) as Timestamp;
+    let worker_index = worker.index();
+    let capture_address = config.capture_address.clone();
 
     let traces = worker.dataflow(move |scope| {
         use differential_dataflow::collection::AsCollection;
-        use timely::dataflow::operators::capture::Replay;
-        use timely::dataflow::operators::Map;
+        use timely::dataflow::operators::capture::{Capture, EventWriter, Replay};
+        use timely::dataflow::operators::{Inspect, Map};
 
         // TODO: Rewrite as one operator with multiple outputs.
         let logs = Some(linked).replay_core(
@@ -40,6 +167,18 @@ pub fn construct<A: Allocate>(
             Some(Duration::from_nanos(config.granularity_ns as u64)),
         );
 
+        // Tee the replayed events to an external capture sink before folding them into
+        // arrangements below.
+        if let Some(address) = &capture_address {
+            let writer = open_capture_sink(address, worker_index);
+            logs.clone().capture_into(EventWriter::new(writer));
+        }
+
+        // Feed the on-demand profiler, so a later Profile command can snapshot it.
+        logs.inspect(move |(_ts, worker, event)| {
+            profiler.borrow_mut().observe(*worker, event);
+        });
+
         // Duration statistics derive from the non-rounded event times.
         let arrangements = logs
             .flat_map(move |(ts, worker, event)| {
@@ -114,12 +253,78 @@ pub fn construct<A: Allocate>(
                 }
             });
 
+        // Counts shortfall events and their cumulative record magnitude per operator.
+        let merge_backlog = logs
+            .flat_map(move |(ts, worker, event)| {
+                let time_ms = ((ts.as_millis() as Timestamp / granularity_ms) + 1) * granularity_ms;
+                if let DifferentialEvent::MergeShortfall(event) = event {
+                    let difference = differential_dataflow::difference::DiffVector::new(vec![
+                        1,
+                        event.number as isize,
+                    ]);
+                    Some(((event.operator, worker), time_ms, difference))
+                } else {
+                    None
+                }
+            })
+            .as_collection()
+            .count_total()
+            .map({
+                let mut row_packer = RowPacker::new();
+                move |((op, worker), count)| {
+                    row_packer.pack(&[
+                        Datum::Int64(op as i64),
+                        Datum::Int64(worker as i64),
+                        Datum::Int64(count[0] as i64),
+                        Datum::Int64(count[1] as i64),
+                    ])
+                }
+            });
+
+        // Pairs each Merge completion with its start and buckets the elapsed time.
+        let merge_duration = logs
+            .flat_map({
+                let mut tracker = MergeDurationTracker::default();
+                move |(ts, worker, event)| {
+                    let time_ms =
+                        ((ts.as_millis() as Timestamp / granularity_ms) + 1) * granularity_ms;
+                    if let DifferentialEvent::Merge(event) = event {
+                        tracker
+                            .observe(worker, ts, &event)
+                            .map(|(op, worker, bucket)| ((op, worker, bucket as isize), time_ms, 1))
+                    } else {
+                        None
+                    }
+                }
+            })
+            .as_collection()
+            .count_total()
+            .map({
+                let mut row_packer = RowPacker::new();
+                move |((op, worker, bucket), count)| {
+                    row_packer.pack(&[
+                        Datum::Int64(op as i64),
+                        Datum::Int64(worker as i64),
+                        Datum::Int64(bucket as i64),
+                        Datum::Int64(count as i64),
+                    ])
+                }
+            });
+
         let logs = vec![
             (
                 LogVariant::Differential(DifferentialLog::Arrangement),
                 arrangements,
             ),
             (LogVariant::Differential(DifferentialLog::Sharing), sharing),
+            (
+                LogVariant::Differential(DifferentialLog::MergeBacklog),
+                merge_backlog,
+            ),
+            (
+                LogVariant::Differential(DifferentialLog::MergeDuration),
+                merge_duration,
+            ),
         ];
 
         use differential_dataflow::operators::arrange::arrangement::ArrangeByKey;
@@ -147,3 +352,205 @@ pub fn construct<A: Allocate>(
 
     traces
 }
+
+/// Tracks live record and batch counts per `(operator, worker)`, fed by [`construct`] via
+/// the same `Batch`/`Merge`/`Drop` accounting that backs the `Arrangement` log variant.
+#[derive(Default)]
+pub struct ArrangementProfiler {
+    counts: std::collections::HashMap<(usize, WorkerIdentifier), (isize, isize)>,
+}
+
+impl ArrangementProfiler {
+    /// Folds a single differential logging event into the running tally.
+    pub fn observe(&mut self, worker: WorkerIdentifier, event: &DifferentialEvent) {
+        let (key, records, batches) = match event {
+            DifferentialEvent::Batch(event) => {
+                ((event.operator, worker), event.length as isize, 1)
+            }
+            DifferentialEvent::Merge(event) => {
+                if let Some(done) = event.complete {
+                    (
+                        (event.operator, worker),
+                        (done as isize) - ((event.length1 + event.length2) as isize),
+                        -1,
+                    )
+                } else {
+                    return;
+                }
+            }
+            DifferentialEvent::Drop(event) => {
+                ((event.operator, worker), -(event.length as isize), -1)
+            }
+            DifferentialEvent::MergeShortfall(_) | DifferentialEvent::TraceShare(_) => return,
+        };
+        let entry = self.counts.entry(key).or_insert((0, 0));
+        entry.0 += records;
+        entry.1 += batches;
+    }
+
+    /// Materializes the current tally as a one-shot snapshot, in the same row shape as the
+    /// continuously-maintained `Arrangement` log variant: `[operator, worker, live_records,
+    /// live_batches]`.
+    pub fn snapshot(&self) -> Vec<repr::Row> {
+        let mut row_packer = RowPacker::new();
+        self.counts
+            .iter()
+            .map(|(&(op, worker), &(records, batches))| {
+                row_packer.pack(&[
+                    Datum::Int64(op as i64),
+                    Datum::Int64(worker as i64),
+                    Datum::Int64(records as i64),
+                    Datum::Int64(batches as i64),
+                ])
+            })
+            .collect()
+    }
+}
+
+/// A control signal, threaded through the logging command path, requesting an on-demand
+/// snapshot of the current arrangement footprint.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ProfileCommand {
+    Snapshot,
+}
+
+/// Dispatches a [`ProfileCommand`] against a running [`ArrangementProfiler`].
+pub fn handle_profile_command(
+    profiler: &ArrangementProfiler,
+    command: ProfileCommand,
+) -> Vec<repr::Row> {
+    match command {
+        ProfileCommand::Snapshot => profiler.snapshot(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_capture_address_file() {
+        assert_eq!(
+            parse_capture_address("file:///var/log/mz/capture"),
+            CaptureTarget::File("/var/log/mz/capture")
+        );
+    }
+
+    #[test]
+    fn parse_capture_address_tcp() {
+        assert_eq!(
+            parse_capture_address("localhost:6667"),
+            CaptureTarget::Tcp("localhost:6667")
+        );
+    }
+
+    #[test]
+    fn latency_bucket_ns_rounds_down_to_power_of_two() {
+        assert_eq!(latency_bucket_ns(0), 0);
+        assert_eq!(latency_bucket_ns(1), 1);
+        assert_eq!(latency_bucket_ns(5), 4);
+        assert_eq!(latency_bucket_ns(1023), 512);
+        assert_eq!(latency_bucket_ns(1024), 1024);
+    }
+
+    fn merge_event(
+        operator: usize,
+        scale: usize,
+        complete: Option<usize>,
+    ) -> differential_dataflow::logging::MergeEvent {
+        differential_dataflow::logging::MergeEvent {
+            operator,
+            scale,
+            length1: 0,
+            length2: 0,
+            complete,
+        }
+    }
+
+    #[test]
+    fn merge_duration_tracker_pairs_start_and_completion() {
+        let mut tracker = MergeDurationTracker::default();
+        let start = merge_event(1, 2, None);
+        let done = merge_event(1, 2, Some(0));
+
+        assert_eq!(tracker.observe(0, Duration::from_nanos(100), &start), None);
+        assert_eq!(
+            tracker.observe(0, Duration::from_nanos(1_124), &done),
+            Some((1, 0, 1024))
+        );
+    }
+
+    #[test]
+    fn merge_duration_tracker_ignores_completion_without_start() {
+        let mut tracker = MergeDurationTracker::default();
+        let done = merge_event(1, 2, Some(0));
+        assert_eq!(tracker.observe(0, Duration::from_nanos(100), &done), None);
+    }
+
+    #[test]
+    fn merge_duration_tracker_disambiguates_by_scale() {
+        let mut tracker = MergeDurationTracker::default();
+        let start_a = merge_event(1, 1, None);
+        let start_b = merge_event(1, 2, None);
+        let done_b = merge_event(1, 2, Some(0));
+
+        assert_eq!(tracker.observe(0, Duration::from_nanos(0), &start_a), None);
+        assert_eq!(tracker.observe(0, Duration::from_nanos(10), &start_b), None);
+        // Completing scale 2 shouldn't consume scale 1's still-open start.
+        assert!(tracker
+            .observe(0, Duration::from_nanos(20), &done_b)
+            .is_some());
+        let done_a = merge_event(1, 1, Some(0));
+        assert!(tracker
+            .observe(0, Duration::from_nanos(30), &done_a)
+            .is_some());
+    }
+
+    #[test]
+    fn merge_duration_tracker_evicts_oldest_start_past_capacity() {
+        let mut tracker = MergeDurationTracker::with_capacity(2);
+        let start_1 = merge_event(1, 1, None);
+        let start_2 = merge_event(2, 1, None);
+        let start_3 = merge_event(3, 1, None);
+
+        tracker.observe(0, Duration::from_nanos(0), &start_1);
+        tracker.observe(0, Duration::from_nanos(10), &start_2);
+        // Past capacity: the oldest pending start (operator 1) is evicted to make room.
+        tracker.observe(0, Duration::from_nanos(20), &start_3);
+
+        let done_1 = merge_event(1, 1, Some(0));
+        assert_eq!(tracker.observe(0, Duration::from_nanos(30), &done_1), None);
+
+        let done_3 = merge_event(3, 1, Some(0));
+        assert!(tracker
+            .observe(0, Duration::from_nanos(40), &done_3)
+            .is_some());
+    }
+
+    #[test]
+    fn arrangement_profiler_tracks_live_counts() {
+        let mut profiler = ArrangementProfiler::default();
+        profiler.observe(
+            0,
+            &DifferentialEvent::Batch(differential_dataflow::logging::BatchEvent {
+                operator: 7,
+                length: 10,
+            }),
+        );
+        profiler.observe(
+            0,
+            &DifferentialEvent::Drop(differential_dataflow::logging::DropEvent {
+                operator: 7,
+                length: 3,
+            }),
+        );
+
+        let rows = handle_profile_command(&profiler, ProfileCommand::Snapshot);
+        assert_eq!(rows.len(), 1);
+        let datums = rows[0].unpack();
+        assert_eq!(datums[0], Datum::Int64(7));
+        assert_eq!(datums[1], Datum::Int64(0));
+        assert_eq!(datums[2], Datum::Int64(7));
+        assert_eq!(datums[3], Datum::Int64(0));
+    }
+}
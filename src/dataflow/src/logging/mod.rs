@@ -0,0 +1,46 @@
+// Copyright Materialize, Inc. All rights reserved.
+//
+// Use of this software is governed by the Business Source License
+// included in the LICENSE file.
+//
+// As of the Change Date specified in that file, in accordance with
+// the Business Source License, use of this software will be governed
+// by the Apache License, Version 2.0.
+
+//! Orchestrates the logging dataflows maintained by each worker.
+
+pub mod differential;
+
+pub use dataflow_types::logging::{DifferentialLog, LogVariant};
+pub use differential::{handle_profile_command, ArrangementProfiler, ProfileCommand};
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+
+use differential_dataflow::logging::DifferentialEvent;
+use timely::communication::Allocate;
+use timely::dataflow::operators::capture::EventLink;
+use timely::logging::WorkerIdentifier;
+
+use crate::arrangement::KeysValsHandle;
+use repr::Timestamp;
+
+/// Constructs this worker's logging dataflows.
+///
+/// Owns the on-demand [`ArrangementProfiler`] that backs a later [`ProfileCommand`] and
+/// returns a handle to it, alongside the usual trace handles, so the caller can register it
+/// wherever it dispatches coordinator commands (see `coord::command::handle_command`).
+pub fn construct<A: Allocate>(
+    worker: &mut timely::worker::Worker<A>,
+    config: &dataflow_types::logging::LoggingConfig,
+    linked: Rc<EventLink<Timestamp, (Duration, WorkerIdentifier, DifferentialEvent)>>,
+) -> (
+    HashMap<LogVariant, (Vec<usize>, KeysValsHandle)>,
+    Rc<RefCell<ArrangementProfiler>>,
+) {
+    let profiler = Rc::new(RefCell::new(ArrangementProfiler::default()));
+    let traces = differential::construct(worker, config, linked, Rc::clone(&profiler));
+    (traces, profiler)
+}